@@ -84,12 +84,215 @@ mod smallvec_impl {
     }
 }
 
+#[cfg(feature = "heapless")]
+mod heapless_impl {
+    use crate::{Context, DeepSizeOf};
+
+    // `heapless` collections store their backing array inline (no heap
+    // allocation), and that inline storage is already counted by
+    // `size_of::<Self>()`. So `deep_size_of_children` is just the sum of
+    // each live element's own children, with no capacity term.
+    impl<T, const N: usize> DeepSizeOf for heapless::Vec<T, N>
+    where
+        T: DeepSizeOf,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            self.iter()
+                .fold(0, |sum, elem| sum + elem.deep_size_of_children(context))
+        }
+    }
+
+    impl<const N: usize> DeepSizeOf for heapless::String<N> {
+        fn deep_size_of_children(&self, _context: &mut Context) -> usize {
+            0
+        }
+    }
+
+    impl<T, K, const N: usize> DeepSizeOf for heapless::binary_heap::BinaryHeap<T, K, N>
+    where
+        T: DeepSizeOf + Ord,
+        K: heapless::binary_heap::Kind,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            self.iter()
+                .fold(0, |sum, elem| sum + elem.deep_size_of_children(context))
+        }
+    }
+
+    impl<K, V, S, const N: usize> DeepSizeOf for heapless::IndexMap<K, V, S, N>
+    where
+        K: DeepSizeOf + Eq + core::hash::Hash,
+        V: DeepSizeOf,
+        S: core::hash::BuildHasher,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            self.iter().fold(0, |sum, (key, val)| {
+                sum + key.deep_size_of_children(context) + val.deep_size_of_children(context)
+            })
+        }
+    }
+
+    impl<T, S, const N: usize> DeepSizeOf for heapless::IndexSet<T, S, N>
+    where
+        T: DeepSizeOf + Eq + core::hash::Hash,
+        S: core::hash::BuildHasher,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            self.iter()
+                .fold(0, |sum, elem| sum + elem.deep_size_of_children(context))
+        }
+    }
+}
+
+#[cfg(feature = "thin_vec")]
+mod thin_vec_impl {
+    use crate::{Context, DeepSizeOf};
+    use core::mem::{align_of, size_of};
+    use thin_vec::ThinVec;
+
+    impl<T> DeepSizeOf for ThinVec<T>
+    where
+        T: DeepSizeOf,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            let child_sizes = self
+                .iter()
+                .fold(0, |sum, elem| sum + elem.deep_size_of_children(context));
+            if self.capacity() == 0 {
+                // An empty ThinVec shares a static singleton allocation, so
+                // it doesn't own any heap memory.
+                return child_sizes;
+            }
+            // ThinVec stores its length and capacity in the heap allocation
+            // itself, as a header (2 usizes) ahead of the elements. Padding
+            // is only inserted between the header and the elements when
+            // `T`'s alignment exceeds the header's size; for ordinary
+            // low-alignment elements the elements start right after it, with
+            // no rounding of the total to `T`'s alignment.
+            let header_size = 2 * size_of::<usize>();
+            let padding = align_of::<T>().saturating_sub(header_size);
+            child_sizes + header_size + padding + self.capacity() * size_of::<T>()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // A test-only element whose own heap footprint is not derived from
+        // its stack layout, so the non-empty test below can't accidentally
+        // pass by conflating the element's heap size with the buffer size.
+        struct Owned {
+            extra_heap_bytes: usize,
+        }
+
+        impl DeepSizeOf for Owned {
+            fn deep_size_of_children(&self, _context: &mut Context) -> usize {
+                self.extra_heap_bytes
+            }
+        }
+
+        #[test]
+        fn empty_thin_vec_shares_the_singleton_and_has_no_heap_size() {
+            let v: ThinVec<Owned> = ThinVec::new();
+            assert_eq!(v.capacity(), 0);
+            assert_eq!(v.deep_size_of_children(&mut Context::new()), 0);
+        }
+
+        #[test]
+        fn non_empty_thin_vec_counts_header_buffer_and_elements() {
+            let mut v: ThinVec<Owned> = ThinVec::new();
+            v.push(Owned {
+                extra_heap_bytes: 10,
+            });
+            v.push(Owned {
+                extra_heap_bytes: 20,
+            });
+            assert!(v.capacity() > 0);
+
+            let header_size = 2 * size_of::<usize>();
+            let padding = align_of::<Owned>().saturating_sub(header_size);
+            let expected_buffer = header_size + padding + v.capacity() * size_of::<Owned>();
+
+            assert_eq!(
+                v.deep_size_of_children(&mut Context::new()),
+                expected_buffer + 30
+            );
+        }
+
+        // Regression test for the header/buffer accounting, using a
+        // byte-sized, byte-aligned element so the elements sit directly
+        // after the 16-byte header with no alignment rounding: measured
+        // against real `thin_vec` allocations, capacities 1/3/5/17 allocate
+        // 17/19/21/33 bytes.
+        #[test]
+        fn non_empty_thin_vec_of_byte_elements_matches_real_allocation_sizes() {
+            for (capacity, expected_allocation) in [(1, 17), (3, 19), (5, 21), (17, 33)] {
+                let mut v: ThinVec<u8> = ThinVec::with_capacity(capacity);
+                for i in 0..capacity {
+                    v.push(i as u8);
+                }
+                assert_eq!(v.capacity(), capacity);
+                assert_eq!(
+                    v.deep_size_of_children(&mut Context::new()),
+                    expected_allocation
+                );
+            }
+        }
+    }
+}
+
+// Shared by the hashbrown-backed collections below (hashbrown itself,
+// indexmap's internal index, and serde_json's preserve_order Map): models
+// hashbrown's real `RawTable` allocation instead of guessing from
+// `capacity()`, which already reflects the post-load-factor usable count
+// and has lost the power-of-two bucket rounding by the time we see it.
+#[cfg(any(feature = "hashbrown", feature = "indexmap", feature = "serde_json"))]
+mod hashbrown_allocation {
+    use core::mem::{align_of, size_of};
+
+    // hashbrown's `Group::WIDTH`: 16 bytes when a SIMD group match is
+    // available (SSE2 on x86/x86_64), and `size_of::<usize>()` for the
+    // portable fallback otherwise.
+    #[cfg(target_feature = "sse2")]
+    pub(crate) const GROUP_WIDTH: usize = 16;
+    #[cfg(not(target_feature = "sse2"))]
+    pub(crate) const GROUP_WIDTH: usize = size_of::<usize>();
+
+    // The number of bucket slots hashbrown allocates to hold `len` elements:
+    // the smallest power of two such that `buckets * 7 / 8 >= len`, with a
+    // floor of 4 for tiny tables (and 8 once a table needs to grow past that).
+    pub(crate) fn buckets_for_len(len: usize) -> usize {
+        if len == 0 {
+            0
+        } else if len < 4 {
+            4
+        } else if len < 8 {
+            8
+        } else {
+            (len * 8 / 7).next_power_of_two()
+        }
+    }
+
+    // The size in bytes of the single allocation backing a hashbrown
+    // `RawTable<T>` holding `len` elements: `buckets` slots of `T` plus
+    // `buckets + GROUP_WIDTH` control bytes, rounded up to `T`'s alignment.
+    pub(crate) fn table_size<T>(len: usize) -> usize {
+        let buckets = buckets_for_len(len);
+        if buckets == 0 {
+            return 0;
+        }
+        let raw_size = buckets * size_of::<T>() + buckets + GROUP_WIDTH;
+        let align = align_of::<T>();
+        (raw_size + align - 1) / align * align
+    }
+}
+
 #[cfg(feature = "hashbrown")]
 mod hashbrown_impl {
+    use super::hashbrown_allocation::table_size;
     use crate::{Context, DeepSizeOf};
-    use core::mem::size_of;
 
-    // This is probably still incorrect, but it's better than before
     impl<K, V, S> DeepSizeOf for hashbrown::HashMap<K, V, S>
     where
         K: DeepSizeOf + Eq + std::hash::Hash,
@@ -99,12 +302,7 @@ mod hashbrown_impl {
         fn deep_size_of_children(&self, context: &mut Context) -> usize {
             self.iter().fold(0, |sum, (key, val)| {
                 sum + key.deep_size_of_children(context) + val.deep_size_of_children(context)
-            }) + self.capacity() * size_of::<(K, V)>()
-            // Buckets would be the more correct value, but there isn't
-            // an API for accessing that with hashbrown.
-            // I believe that hashbrown's HashTable is represented as
-            // an array of (K, V), with control bytes at the start/end
-            // that mark used/uninitialized buckets (?)
+            }) + table_size::<(K, V)>(self.len())
         }
     }
 
@@ -116,22 +314,75 @@ mod hashbrown_impl {
         fn deep_size_of_children(&self, context: &mut Context) -> usize {
             self.iter()
                 .fold(0, |sum, key| sum + key.deep_size_of_children(context))
-                + self.capacity() * size_of::<K>()
+                + table_size::<K>(self.len())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::hashbrown_allocation::{buckets_for_len, table_size, GROUP_WIDTH};
+        use core::mem::size_of;
+
+        // hashbrown grows a table to exactly `capacity_to_buckets(new_len)`
+        // buckets each time it needs more room, so building a map by
+        // inserting one element at a time lands on the same bucket count
+        // our estimate predicts for that final length.
+        fn real_capacity(len: usize) -> usize {
+            let mut map = hashbrown::HashMap::<u64, u64>::new();
+            for i in 0..len {
+                map.insert(i as u64, i as u64);
+            }
+            map.capacity()
+        }
+
+        fn capacity_for_buckets(buckets: usize) -> usize {
+            if buckets < 8 {
+                buckets.saturating_sub(1)
+            } else {
+                buckets * 7 / 8
+            }
+        }
+
+        #[test]
+        fn estimated_buckets_match_a_freshly_built_maps_capacity() {
+            for len in [0usize, 1, 2, 3, 4, 5, 7, 8, 9, 15, 16, 17, 100, 1000] {
+                let buckets = buckets_for_len(len);
+                assert_eq!(
+                    capacity_for_buckets(buckets),
+                    real_capacity(len),
+                    "bucket estimate diverged from hashbrown's own capacity for len={len}",
+                );
+            }
+        }
+
+        #[test]
+        fn table_size_is_data_region_plus_rounded_control_bytes() {
+            for len in [0usize, 1, 4, 8, 20, 1000] {
+                let buckets = buckets_for_len(len);
+                let expected = if buckets == 0 {
+                    0
+                } else {
+                    let raw = buckets * size_of::<(u64, u64)>() + buckets + GROUP_WIDTH;
+                    let align = core::mem::align_of::<(u64, u64)>();
+                    (raw + align - 1) / align * align
+                };
+                assert_eq!(table_size::<(u64, u64)>(len), expected);
+            }
         }
     }
 }
 
 #[cfg(feature = "indexmap")]
 mod indexmap_impl {
+    use super::hashbrown_allocation::table_size;
     use crate::{Context, DeepSizeOf};
     use core::mem::size_of;
     use indexmap::{IndexMap, IndexSet};
 
-    // IndexMap uses a vec of buckets (usize, K, V) as backing, with
-    // a hashbrown::RawTable<usize> for lookups.  This method will
-    // consistently underestimate, because IndexMap::capacity will
-    // return the min of the capacity of the buckets list and the
-    // capacity of the raw table.
+    // IndexMap stores its entries in a plain `Vec<(hash, K, V)>` and keeps a
+    // separate hashbrown `RawTable<usize>` mapping hashes to indices into
+    // that vec. The entries vec is sized like any other `Vec`; the index
+    // table gets the accurate hashbrown allocation model.
     impl<K, V, S> DeepSizeOf for IndexMap<K, V, S>
     where
         K: DeepSizeOf,
@@ -141,8 +392,9 @@ mod indexmap_impl {
             let child_sizes = self.iter().fold(0, |sum, (key, val)| {
                 sum + key.deep_size_of_children(context) + val.deep_size_of_children(context)
             });
-            let map_size = self.capacity() * (size_of::<(usize, K, V)>() + size_of::<usize>());
-            child_sizes + map_size
+            let entries_size = self.capacity() * size_of::<(u64, K, V)>();
+            let index_size = table_size::<usize>(self.len());
+            child_sizes + entries_size + index_size
         }
     }
     impl<K, S> DeepSizeOf for IndexSet<K, S>
@@ -153,8 +405,48 @@ mod indexmap_impl {
             let child_sizes = self
                 .iter()
                 .fold(0, |sum, key| sum + key.deep_size_of_children(context));
-            let map_size = self.capacity() * (size_of::<(usize, K, ())>() + size_of::<usize>());
-            child_sizes + map_size
+            let entries_size = self.capacity() * size_of::<(u64, K)>();
+            let index_size = table_size::<usize>(self.len());
+            child_sizes + entries_size + index_size
+        }
+    }
+}
+
+#[cfg(feature = "internment")]
+mod internment_impl {
+    use crate::{Context, DeepSizeOf};
+    use core::mem::size_of;
+
+    // `Intern`/`ArcIntern` hand out many handles to the same backing
+    // allocation (arena-leaked or `Arc`-owned). We only want to count the
+    // pointee once, so the first handle we see for a given pointer counts
+    // the full pointee, and every later handle to that same pointer
+    // contributes nothing extra; the pointer is recorded with `Context::add`.
+    impl<T> DeepSizeOf for internment::Intern<T>
+    where
+        T: DeepSizeOf + Eq + std::hash::Hash + Send + Sync + 'static,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            let ptr = &**self as *const T as usize;
+            if context.add(ptr) {
+                size_of::<T>() + (**self).deep_size_of_children(context)
+            } else {
+                0
+            }
+        }
+    }
+
+    impl<T> DeepSizeOf for internment::ArcIntern<T>
+    where
+        T: DeepSizeOf + Eq + std::hash::Hash + Send + Sync + 'static,
+    {
+        fn deep_size_of_children(&self, context: &mut Context) -> usize {
+            let ptr = &**self as *const T as usize;
+            if context.add(ptr) {
+                size_of::<T>() + (**self).deep_size_of_children(context)
+            } else {
+                0
+            }
         }
     }
 }
@@ -248,14 +540,16 @@ mod serde_json_impl {
                 )>();
                 element_size + self.len() * overhead * 2 / (BTREE_MAX + BTREE_MIN)
             } else {
-                // Then it's an IndexMap actually
+                // Then it's an IndexMap actually. `Map` doesn't expose the
+                // underlying IndexMap's `capacity()`, so the entries vec is
+                // approximated at exactly `len`; the index table uses the
+                // accurate hashbrown allocation model.
                 let child_sizes = self.iter().fold(0, |sum, (key, val)| {
                     sum + key.deep_size_of_children(context) + val.deep_size_of_children(context)
                 });
-                let estimated_cap = self.len().saturating_mul(2);
-                let map_size =
-                    estimated_cap * (size_of::<(usize, String, Value)>() + size_of::<usize>());
-                child_sizes + map_size
+                let entries_size = self.len() * size_of::<(u64, String, Value)>();
+                let index_size = super::hashbrown_allocation::table_size::<usize>(self.len());
+                child_sizes + entries_size + index_size
             }
         }
     }
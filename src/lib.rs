@@ -0,0 +1,156 @@
+//! `deepsize` measures the total size of a value, including heap
+//! allocations reachable through it (`Vec` backing buffers, `Box`ed data,
+//! `Rc`/`Arc` pointees, etc), not just its `size_of::<T>()` stack footprint.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::collections::BTreeSet;
+use core::mem::size_of;
+
+mod external_impls;
+
+// Only used by the `serde_json` impl to estimate `BTreeMap`'s node layout.
+pub(crate) const BTREE_B: usize = 6;
+pub(crate) const BTREE_MAX: usize = 2 * BTREE_B - 1;
+pub(crate) const BTREE_MIN: usize = BTREE_B - 1;
+
+/// Implement `DeepSizeOf` for one or more types whose children never
+/// contribute any heap size (e.g. `Copy` types with no indirection).
+///
+/// ```ignore
+/// known_deep_size!(0; u8, u16, SomeType);
+/// known_deep_size!(0; { T: SomeBound } Generic<T>);
+/// ```
+#[macro_export]
+macro_rules! known_deep_size {
+    ($size:expr; $($rest:tt)*) => {
+        $crate::known_deep_size!(@items $size; $($rest)*);
+    };
+    (@items $size:expr;) => {};
+    (@items $size:expr; { $($bound:tt)* } $ty:ty $(, $($rest:tt)*)?) => {
+        impl<$($bound)*> $crate::DeepSizeOf for $ty {
+            fn deep_size_of_children(&self, _context: &mut $crate::Context) -> usize {
+                $size
+            }
+        }
+        $crate::known_deep_size!(@items $size; $($($rest)*)?);
+    };
+    (@items $size:expr; $ty:ty $(, $($rest:tt)*)?) => {
+        impl $crate::DeepSizeOf for $ty {
+            fn deep_size_of_children(&self, _context: &mut $crate::Context) -> usize {
+                $size
+            }
+        }
+        $crate::known_deep_size!(@items $size; $($($rest)*)?);
+    };
+}
+
+known_deep_size!(0;
+    (), bool, char,
+    u8, u16, u32, u64, u128, usize,
+    i8, i16, i32, i64, i128, isize,
+    f32, f64,
+);
+
+/// Tracks which allocations have already been counted while walking a value,
+/// so that shared pointers (`Rc`/`Arc`, and interned handles) are only
+/// counted once no matter how many live handles point to them.
+pub struct Context {
+    visited: BTreeSet<usize>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context {
+            visited: BTreeSet::new(),
+        }
+    }
+
+    /// Records that the allocation at `ptr` has been visited. Returns
+    /// `true` the first time a given pointer is seen (the caller should
+    /// count the pointee), and `false` on every later call for the same
+    /// pointer (e.g. another `Rc`/`Arc` handle to the same allocation).
+    pub fn add(&mut self, ptr: usize) -> bool {
+        self.visited.insert(ptr)
+    }
+
+    /// Like [`Context::add`], but for deduplicated interned handles:
+    /// returns `size` the first time `ptr` is seen, and `0` on every later
+    /// call, so the pointee contributes exactly once regardless of how many
+    /// interned handles reference it.
+    pub fn add_interned(&mut self, ptr: usize, size: usize) -> usize {
+        if self.add(ptr) {
+            size
+        } else {
+            0
+        }
+    }
+}
+
+/// The stack vs. heap breakdown of a value's deep size, as returned by
+/// [`DeepSizeOf::size_breakdown`]. `total() == size_of::<Self>() + heap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBreakdown {
+    /// The value's own `size_of::<Self>()` footprint.
+    pub stack: usize,
+    /// Everything the value owns on the heap (allocations reachable
+    /// through pointers), not including its own stack footprint.
+    pub heap: usize,
+}
+
+impl SizeBreakdown {
+    /// The total deep size: `stack + heap`.
+    pub fn total(&self) -> usize {
+        self.stack + self.heap
+    }
+}
+
+/// A type whose deep size (including heap allocations it owns) can be
+/// measured.
+pub trait DeepSizeOf {
+    /// The total size of this value: its own stack footprint plus
+    /// everything it owns on the heap.
+    fn deep_size_of(&self) -> usize
+    where
+        Self: Sized,
+    {
+        self.size_breakdown().total()
+    }
+
+    /// The size of everything this value owns on the heap, not including
+    /// its own `size_of::<Self>()` stack footprint. Useful when the value
+    /// lives inline in a larger structure, where only the heap portion is
+    /// actually "extra".
+    fn heap_size_of(&self) -> usize
+    where
+        Self: Sized,
+    {
+        self.deep_size_of_children(&mut Context::new())
+    }
+
+    /// The stack/heap breakdown of this value's deep size.
+    fn size_breakdown(&self) -> SizeBreakdown
+    where
+        Self: Sized,
+    {
+        SizeBreakdown {
+            stack: size_of::<Self>(),
+            heap: self.heap_size_of(),
+        }
+    }
+
+    /// The size of everything this value owns on the heap, given a
+    /// [`Context`] tracking which shared allocations have already been
+    /// counted. `heap_size_of` and `size_breakdown` are built on top of
+    /// this; implementors only ever need to provide this one method.
+    fn deep_size_of_children(&self, context: &mut Context) -> usize;
+}